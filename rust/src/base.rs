@@ -43,6 +43,10 @@ pub struct Handle<'a> {
         event::EventHandle,
         Box<Box<dyn Fn(guestfs::Event, event::EventHandle, &[u8], &[u64]) + 'a>>,
     >,
+    pub(crate) callbacks_send: collections::HashMap<
+        event::EventHandle,
+        Box<Box<dyn Fn(guestfs::Event, event::EventHandle, &[u8], &[u64]) + Send + Sync + 'a>>,
+    >,
 }
 
 impl<'a> Handle<'a> {
@@ -52,7 +56,12 @@ impl<'a> Handle<'a> {
             Err(error::Error::Create)
         } else {
             let callbacks = collections::HashMap::new();
-            Ok(Handle { g, callbacks })
+            let callbacks_send = collections::HashMap::new();
+            Ok(Handle {
+                g,
+                callbacks,
+                callbacks_send,
+            })
         }
     }
 
@@ -62,7 +71,12 @@ impl<'a> Handle<'a> {
             Err(error::Error::Create)
         } else {
             let callbacks = collections::HashMap::new();
-            Ok(Handle { g, callbacks })
+            let callbacks_send = collections::HashMap::new();
+            Ok(Handle {
+                g,
+                callbacks,
+                callbacks_send,
+            })
         }
     }
 }
@@ -37,6 +37,65 @@ pub struct APIError {
     errno: i32,
 }
 
+/// The common libc errno values that the libguestfs daemon reports back
+/// through `guestfs_last_errno`, classified so callers can `match` on the
+/// failure kind instead of hardcoding platform integers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GuestfsErrno {
+    NoSpace,
+    NotFound,
+    Perm,
+    Exists,
+    NotSupported,
+    Io,
+    Other(i32),
+}
+
+// Standard errno values on Linux, where the libguestfs appliance runs.
+// ENOTSUP and EOPNOTSUPP share the same value (95) on Linux, so only one
+// constant is needed here.
+const ENOSPC: i32 = 28;
+const ENOENT: i32 = 2;
+const EPERM: i32 = 1;
+const EEXIST: i32 = 17;
+const ENOTSUP: i32 = 95;
+const EIO: i32 = 5;
+
+impl GuestfsErrno {
+    fn from_errno(errno: i32) -> GuestfsErrno {
+        match errno {
+            ENOSPC => GuestfsErrno::NoSpace,
+            ENOENT => GuestfsErrno::NotFound,
+            EPERM => GuestfsErrno::Perm,
+            EEXIST => GuestfsErrno::Exists,
+            ENOTSUP => GuestfsErrno::NotSupported,
+            EIO => GuestfsErrno::Io,
+            _ => GuestfsErrno::Other(errno),
+        }
+    }
+}
+
+impl APIError {
+    /// Classify the raw errno returned by the appliance.
+    pub fn kind(&self) -> GuestfsErrno {
+        GuestfsErrno::from_errno(self.errno)
+    }
+
+    /// Map the raw errno to the closest `std::io::ErrorKind`, for callers
+    /// that want to compose with `std::io`-based error handling.
+    pub fn as_io_error_kind(&self) -> std::io::ErrorKind {
+        match self.kind() {
+            GuestfsErrno::NoSpace => std::io::ErrorKind::Other,
+            GuestfsErrno::NotFound => std::io::ErrorKind::NotFound,
+            GuestfsErrno::Perm => std::io::ErrorKind::PermissionDenied,
+            GuestfsErrno::Exists => std::io::ErrorKind::AlreadyExists,
+            GuestfsErrno::NotSupported => std::io::ErrorKind::Other,
+            GuestfsErrno::Io => std::io::ErrorKind::Other,
+            GuestfsErrno::Other(_) => std::io::ErrorKind::Other,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
     API(APIError),
@@ -110,3 +169,47 @@ impl<'a> base::Handle<'a> {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(errno: i32) -> APIError {
+        APIError {
+            operation: "test",
+            message: "test".to_string(),
+            errno,
+        }
+    }
+
+    #[test]
+    fn classifies_common_errnos() {
+        assert_eq!(api_error(ENOSPC).kind(), GuestfsErrno::NoSpace);
+        assert_eq!(api_error(ENOENT).kind(), GuestfsErrno::NotFound);
+        assert_eq!(api_error(EPERM).kind(), GuestfsErrno::Perm);
+        assert_eq!(api_error(EEXIST).kind(), GuestfsErrno::Exists);
+        assert_eq!(api_error(ENOTSUP).kind(), GuestfsErrno::NotSupported);
+        assert_eq!(api_error(EIO).kind(), GuestfsErrno::Io);
+    }
+
+    #[test]
+    fn falls_back_to_other_for_unknown_errno() {
+        assert_eq!(api_error(12345).kind(), GuestfsErrno::Other(12345));
+    }
+
+    #[test]
+    fn maps_to_the_closest_io_error_kind() {
+        assert_eq!(
+            api_error(ENOENT).as_io_error_kind(),
+            std::io::ErrorKind::NotFound
+        );
+        assert_eq!(
+            api_error(EPERM).as_io_error_kind(),
+            std::io::ErrorKind::PermissionDenied
+        );
+        assert_eq!(
+            api_error(EEXIST).as_io_error_kind(),
+            std::io::ErrorKind::AlreadyExists
+        );
+    }
+}
@@ -49,11 +49,17 @@ extern "C" {
     fn free(buf: *const c_void);
 }
 
-#[derive(Hash, PartialEq, Eq)]
+#[derive(Hash, PartialEq, Eq, Clone, Copy)]
 pub struct EventHandle {
     eh: i32,
 }
 
+impl EventHandle {
+    pub(crate) fn raw(&self) -> i32 {
+        self.eh
+    }
+}
+
 fn events_to_bitmask(v: &[guestfs::Event]) -> u64 {
     let mut r = 0u64;
     for x in v.iter() {
@@ -140,11 +146,84 @@ impl<'a> base::Handle<'a> {
         Ok(EventHandle { eh })
     }
 
+    /// Like `set_event_callback`, but the closure must also be `Send +
+    /// Sync`, and it is stored behind `Box<dyn Fn + Send + Sync>` (in the
+    /// handle's separate `callbacks_send` map) instead of the plain
+    /// `Box<dyn Fn>` used by `set_event_callback`, so the closure itself
+    /// is safe to invoke from a thread other than the one that registered
+    /// it.
+    ///
+    /// This does not make the `Handle` shareable between threads: it
+    /// still wraps a raw `*mut guestfs_h` and so remains `!Send`/`!Sync`,
+    /// so it cannot be shared through an `Arc` across a worker pool, and
+    /// this method, like every other operation on the handle (launching
+    /// the appliance, running actions, closing the handle), runs on the
+    /// thread that owns the handle.
+    pub fn set_event_callback_send<C: 'a>(
+        &mut self,
+        callback: C,
+        events: &[guestfs::Event],
+    ) -> Result<EventHandle, error::Error>
+    where
+        C: Fn(guestfs::Event, EventHandle, &[u8], &[u64]) + Send + Sync + 'a,
+    {
+        extern "C" fn trampoline<C>(
+            _g: *const base::guestfs_h,
+            opaque: *const c_void,
+            event: u64,
+            event_handle: i32,
+            _flags: i32,
+            buf: *const c_char,
+            buf_len: usize,
+            array: *const u64,
+            array_len: usize,
+        ) where
+            C: Fn(guestfs::Event, EventHandle, &[u8], &[u64]) + Send + Sync,
+        {
+            let event = match guestfs::Event::from_bitmask(event) {
+                Some(x) => x,
+                None => panic!("Failed to parse bitmask: {}", event),
+            };
+            let eh = EventHandle { eh: event_handle };
+            let buf = unsafe { slice::from_raw_parts(buf as *const u8, buf_len) };
+            let array = unsafe { slice::from_raw_parts(array, array_len) };
+
+            let callback: &Box<dyn Fn(guestfs::Event, EventHandle, &[u8], &[u64]) + Send + Sync> =
+                Box::leak(unsafe { Box::from_raw(opaque as *mut _) });
+            callback(event, eh, buf, array)
+        }
+
+        let callback: Box<Box<dyn Fn(guestfs::Event, EventHandle, &[u8], &[u64]) + Send + Sync + 'a>> =
+            Box::new(Box::new(callback));
+        let ptr = Box::into_raw(callback);
+        let callback = unsafe { Box::from_raw(ptr) };
+        let event_bitmask = events_to_bitmask(events);
+
+        let eh = {
+            unsafe {
+                guestfs_set_event_callback(
+                    self.g,
+                    trampoline::<C>,
+                    event_bitmask,
+                    0,
+                    ptr as *const c_void,
+                )
+            }
+        };
+        if eh == -1 {
+            return Err(self.get_error_from_handle("set_event_callback_send"));
+        }
+        self.callbacks_send.insert(EventHandle { eh }, callback);
+
+        Ok(EventHandle { eh })
+    }
+
     pub fn delete_event_callback(&mut self, eh: EventHandle) -> Result<(), error::Error> {
         unsafe {
             guestfs_delete_event_callback(self.g, eh.eh);
         }
         self.callbacks.remove(&eh);
+        self.callbacks_send.remove(&eh);
         Ok(())
     }
 }
@@ -0,0 +1,97 @@
+/* libguestfs Rust bindings
+ * Copyright (C) 2019 Hiroyuki Katsura <hiroyuki.katsura.0513@gmail.com>
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this library; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA
+ */
+
+use crate::base;
+use crate::error;
+use crate::event;
+use crate::guestfs;
+use futures::channel::mpsc;
+use futures::stream::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+#[link(name = "guestfs")]
+extern "C" {
+    fn guestfs_delete_event_callback(g: *const base::guestfs_h, eh: i32);
+}
+
+/// An asynchronous stream of events delivered by the appliance.
+///
+/// Obtained from `Handle::event_stream`. Each item is the same
+/// `(event, buf, array)` triple that the synchronous callback
+/// registered by `set_event_callback` would otherwise receive, but
+/// delivered through a `futures::Stream` so it can be driven with
+/// `while let Some(ev) = stream.next().await` from a tokio/async-std
+/// task instead of a blocking closure.
+///
+/// `EventStream` holds a shared `&'b Handle<'a>`, but `event_stream`
+/// takes `&'b mut self`, so the handle stays exclusively borrowed for as
+/// long as the stream is alive: the handle cannot be closed, and the
+/// callback it registered cannot be invalidated, while the stream
+/// exists, and no other method of the handle (the `&self` action calls
+/// included) can be called until the stream is dropped.
+pub struct EventStream<'a, 'b> {
+    handle: &'b base::Handle<'a>,
+    eh: Option<event::EventHandle>,
+    receiver: mpsc::UnboundedReceiver<(guestfs::Event, Vec<u8>, Vec<u64>)>,
+}
+
+impl<'a> base::Handle<'a> {
+    /// Subscribe to `events` and return a `Stream` of the events as they
+    /// arrive, instead of driving a synchronous callback.
+    pub fn event_stream<'b>(
+        &'b mut self,
+        events: &[guestfs::Event],
+    ) -> Result<EventStream<'a, 'b>, error::Error> {
+        let (sender, receiver) = mpsc::unbounded();
+        let eh = self.set_event_callback(
+            move |event, _eh, buf, array| {
+                // If this fails the receiving end has already been
+                // dropped, in which case there is nothing useful to do.
+                let _ = sender.unbounded_send((event, buf.to_vec(), array.to_vec()));
+            },
+            events,
+        )?;
+        Ok(EventStream {
+            handle: self,
+            eh: Some(eh),
+            receiver,
+        })
+    }
+}
+
+impl<'a, 'b> Stream for EventStream<'a, 'b> {
+    type Item = (guestfs::Event, Vec<u8>, Vec<u64>);
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.receiver).poll_next(cx)
+    }
+}
+
+impl<'a, 'b> Drop for EventStream<'a, 'b> {
+    fn drop(&mut self) {
+        if let Some(eh) = self.eh.take() {
+            // Unregisters the callback at the C level directly: going
+            // through the safe `delete_event_callback` would need a
+            // `&mut Handle`, which we deliberately don't hold. The entry
+            // this left behind in the handle's `callbacks` map is
+            // cleaned up when the handle itself is dropped.
+            unsafe { guestfs_delete_event_callback(self.handle.g, eh.raw()) };
+        }
+    }
+}
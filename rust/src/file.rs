@@ -0,0 +1,176 @@
+/* libguestfs Rust bindings
+ * Copyright (C) 2019 Hiroyuki Katsura <hiroyuki.katsura.0513@gmail.com>
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this library; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA
+ */
+
+use crate::base;
+use crate::error;
+use std::cmp;
+use std::ffi;
+use std::io;
+use std::os::raw::{c_char, c_void};
+use std::slice;
+
+// A few MB per call, well under the libguestfs daemon message size limit.
+const CHUNK_SIZE: usize = 2 * 1024 * 1024;
+
+#[link(name = "guestfs")]
+extern "C" {
+    fn guestfs_pwrite(
+        g: *mut base::guestfs_h,
+        path: *const c_char,
+        content: *const c_char,
+        size: usize,
+        offset: i64,
+    ) -> i32;
+    fn guestfs_pread(
+        g: *mut base::guestfs_h,
+        path: *const c_char,
+        count: usize,
+        offset: i64,
+        size_r: *mut usize,
+    ) -> *mut c_char;
+    fn guestfs_filesize(g: *mut base::guestfs_h, path: *const c_char) -> i64;
+    fn free(buf: *const c_void);
+}
+
+fn to_io_error(err: error::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err.to_string())
+}
+
+/// A file inside the appliance, obtained from `Handle::open_read`,
+/// `Handle::open_write` or `Handle::open_append`.
+///
+/// `GuestFile` implements `Read`, `Write` and `Seek` on top of the
+/// underlying `pread`/`pwrite`/`filesize` calls, chunking each one at
+/// `CHUNK_SIZE` bytes and tracking its own cursor, so a guest file can be
+/// handed to anything written against the standard I/O traits
+/// (`BufReader`, `std::io::copy`, `serde_json::from_reader`, ...).
+pub struct GuestFile<'a, 'b> {
+    handle: &'b base::Handle<'a>,
+    path: ffi::CString,
+    pos: u64,
+}
+
+impl<'a> base::Handle<'a> {
+    /// Open `path` for reading, with the cursor at the start of the file.
+    pub fn open_read<'b>(&'b self, path: &str) -> Result<GuestFile<'a, 'b>, error::Error> {
+        Ok(GuestFile {
+            handle: self,
+            path: ffi::CString::new(path)?,
+            pos: 0,
+        })
+    }
+
+    /// Open `path` for writing, with the cursor at the start of the file.
+    pub fn open_write<'b>(&'b self, path: &str) -> Result<GuestFile<'a, 'b>, error::Error> {
+        Ok(GuestFile {
+            handle: self,
+            path: ffi::CString::new(path)?,
+            pos: 0,
+        })
+    }
+
+    /// Open `path` for writing, with the cursor at the end of the file.
+    pub fn open_append<'b>(&'b self, path: &str) -> Result<GuestFile<'a, 'b>, error::Error> {
+        let path = ffi::CString::new(path)?;
+        let size = unsafe { guestfs_filesize(self.g, path.as_ptr()) };
+        if size == -1 {
+            return Err(self.get_error_from_handle("filesize"));
+        }
+        Ok(GuestFile {
+            handle: self,
+            path,
+            pos: size as u64,
+        })
+    }
+}
+
+impl<'a, 'b> GuestFile<'a, 'b> {
+    fn filesize(&self) -> io::Result<u64> {
+        let size = unsafe { guestfs_filesize(self.handle.g, self.path.as_ptr()) };
+        if size == -1 {
+            return Err(to_io_error(self.handle.get_error_from_handle("filesize")));
+        }
+        Ok(size as u64)
+    }
+}
+
+impl<'a, 'b> io::Read for GuestFile<'a, 'b> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = cmp::min(buf.len(), CHUNK_SIZE);
+        let mut size_r: usize = 0;
+        let ptr = unsafe {
+            guestfs_pread(
+                self.handle.g,
+                self.path.as_ptr(),
+                count,
+                self.pos as i64,
+                &mut size_r,
+            )
+        };
+        if ptr.is_null() {
+            return Err(to_io_error(self.handle.get_error_from_handle("pread")));
+        }
+        let data = unsafe { slice::from_raw_parts(ptr as *const u8, size_r) };
+        buf[..size_r].copy_from_slice(data);
+        unsafe { free(ptr as *const c_void) };
+        self.pos += size_r as u64;
+        Ok(size_r)
+    }
+}
+
+impl<'a, 'b> io::Write for GuestFile<'a, 'b> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = cmp::min(buf.len(), CHUNK_SIZE);
+        let written = unsafe {
+            guestfs_pwrite(
+                self.handle.g,
+                self.path.as_ptr(),
+                buf.as_ptr() as *const c_char,
+                n,
+                self.pos as i64,
+            )
+        };
+        if written == -1 {
+            return Err(to_io_error(self.handle.get_error_from_handle("pwrite")));
+        }
+        self.pos += written as u64;
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<'a, 'b> io::Seek for GuestFile<'a, 'b> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            io::SeekFrom::Start(offset) => offset as i64,
+            io::SeekFrom::Current(offset) => self.pos as i64 + offset,
+            io::SeekFrom::End(offset) => self.filesize()? as i64 + offset,
+        };
+        if new_pos < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative position",
+            ));
+        }
+        self.pos = new_pos as u64;
+        Ok(self.pos)
+    }
+}
@@ -0,0 +1,287 @@
+/* libguestfs Rust bindings
+ * Copyright (C) 2019 Hiroyuki Katsura <hiroyuki.katsura.0513@gmail.com>
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this library; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA
+ */
+
+use crate::base;
+use crate::error;
+use crate::vfs::{FileStat, GuestVfs};
+use regex::Regex;
+use std::vec;
+
+/// One path that matched a `find_matching` pattern, together with its
+/// (un-followed) stat information.
+pub struct FindMatch {
+    pub path: String,
+    pub stat: FileStat,
+}
+
+/// An iterator over the paths, relative to `root`, that satisfy a glob
+/// pattern compiled by `Handle::find_matching`. Results are produced
+/// eagerly (one `readdir` per directory visited) and handed out in
+/// deterministic, sorted order.
+pub struct FindMatching {
+    matches: vec::IntoIter<FindMatch>,
+}
+
+impl Iterator for FindMatching {
+    type Item = FindMatch;
+
+    fn next(&mut self) -> Option<FindMatch> {
+        self.matches.next()
+    }
+}
+
+/// Translate a shell-style glob into an anchored regex fragment.
+///
+/// Supports `*` (any characters except `/`), `?` (a single character
+/// except `/`), `**` (crosses directory separators), `[a-z]`/`[!...]`
+/// character classes, and brace alternation `{a,b}`.
+fn glob_to_regex(pattern: &str) -> String {
+    let chars: Vec<char> = pattern.chars().collect();
+    let mut out = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' => {
+                if i + 1 < chars.len() && chars[i + 1] == '*' {
+                    if i + 2 < chars.len() && chars[i + 2] == '/' {
+                        // `**/` matches zero or more whole path
+                        // components, including none at all, so
+                        // `**/b.txt` must also match the top-level
+                        // `b.txt` rather than requiring a literal `/`.
+                        out.push_str("(?:.*/)?");
+                        i += 3;
+                    } else {
+                        out.push_str(".*");
+                        i += 2;
+                    }
+                } else {
+                    out.push_str("[^/]*");
+                    i += 1;
+                }
+            }
+            '?' => {
+                out.push_str("[^/]");
+                i += 1;
+            }
+            '[' => {
+                let start = i;
+                i += 1;
+                if i < chars.len() && chars[i] == '!' {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i] != ']' {
+                    i += 1;
+                }
+                if i < chars.len() {
+                    i += 1; // consume the closing ']'
+                }
+                let class: String = chars[start..i].iter().collect();
+                if let Some(rest) = class.strip_prefix("[!") {
+                    out.push('[');
+                    out.push('^');
+                    out.push_str(rest);
+                } else {
+                    out.push_str(&class);
+                }
+            }
+            '{' => {
+                let start = i + 1;
+                i += 1;
+                while i < chars.len() && chars[i] != '}' {
+                    i += 1;
+                }
+                let alts: String = chars[start..i].iter().collect();
+                if i < chars.len() {
+                    i += 1; // consume the closing '}'
+                }
+                out.push_str("(?:");
+                let parts: Vec<String> = alts.split(',').map(regex::escape).collect();
+                out.push_str(&parts.join("|"));
+                out.push(')');
+            }
+            c => {
+                if "\\.+^$()|".contains(c) {
+                    out.push('\\');
+                }
+                out.push(c);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// The literal directory prefix that every match is guaranteed to start
+/// with, i.e. everything up to the last `/` before the first wildcard.
+/// Used to prune subtrees that cannot possibly contain a match without
+/// having to evaluate the full pattern against them.
+fn literal_dir_prefix(pattern: &str) -> &str {
+    let end = pattern
+        .find(|c| matches!(c, '*' | '?' | '[' | '{'))
+        .unwrap_or(pattern.len());
+    match pattern[..end].rfind('/') {
+        Some(idx) => &pattern[..idx],
+        None => "",
+    }
+}
+
+fn compatible(rel: &str, literal_dir_prefix: &str) -> bool {
+    if literal_dir_prefix.is_empty() || rel.is_empty() {
+        return true;
+    }
+    if rel.len() <= literal_dir_prefix.len() {
+        literal_dir_prefix.starts_with(rel)
+    } else {
+        rel.starts_with(literal_dir_prefix) && rel.as_bytes()[literal_dir_prefix.len()] == b'/'
+    }
+}
+
+impl<'a> base::Handle<'a> {
+    /// Recursively walk `root`, returning every entry whose path relative
+    /// to `root` matches the glob `pattern`, together with its stat info.
+    pub fn find_matching(
+        &self,
+        root: &str,
+        pattern: &str,
+    ) -> Result<FindMatching, error::Error> {
+        let regex = Regex::new(&format!("^{}$", glob_to_regex(pattern)))
+            .map_err(|_| error::unix_error("find_matching"))?;
+        let prefix = literal_dir_prefix(pattern);
+
+        let mut out = Vec::new();
+        self.find_matching_walk(root, "", &regex, prefix, &mut out)?;
+        out.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(FindMatching {
+            matches: out.into_iter(),
+        })
+    }
+
+    fn find_matching_walk(
+        &self,
+        root: &str,
+        rel: &str,
+        regex: &Regex,
+        prefix: &str,
+        out: &mut Vec<FindMatch>,
+    ) -> Result<(), error::Error> {
+        if !compatible(rel, prefix) {
+            return Ok(());
+        }
+
+        let dir = if rel.is_empty() {
+            root.to_string()
+        } else {
+            format!("{}/{}", root, rel)
+        };
+        let mut entries = GuestVfs::read_dir(self, &dir)?;
+        entries.sort();
+
+        for name in entries {
+            let child_rel = if rel.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", rel, name)
+            };
+            if !compatible(&child_rel, prefix) {
+                continue;
+            }
+            let child_path = format!("{}/{}", root, child_rel);
+            let stat = GuestVfs::lstat(self, &child_path)?;
+            if regex.is_match(&child_rel) {
+                out.push(FindMatch {
+                    path: child_rel.clone(),
+                    stat,
+                });
+            }
+            if stat.is_dir && !stat.is_symlink {
+                self.find_matching_walk(root, &child_rel, regex, prefix, out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, candidate: &str) -> bool {
+        Regex::new(&format!("^{}$", glob_to_regex(pattern)))
+            .unwrap()
+            .is_match(candidate)
+    }
+
+    #[test]
+    fn star_does_not_cross_slash() {
+        assert!(matches("*.txt", "a.txt"));
+        assert!(!matches("*.txt", "a/b.txt"));
+    }
+
+    #[test]
+    fn double_star_crosses_slash() {
+        assert!(matches("**/b.txt", "a/b.txt"));
+        assert!(matches("**/b.txt", "a/c/b.txt"));
+        assert!(matches("**/b.txt", "b.txt"));
+    }
+
+    #[test]
+    fn question_mark_matches_single_char() {
+        assert!(matches("a?c", "abc"));
+        assert!(!matches("a?c", "ac"));
+        assert!(!matches("a?c", "a/c"));
+    }
+
+    #[test]
+    fn character_class() {
+        assert!(matches("[a-c].txt", "b.txt"));
+        assert!(!matches("[a-c].txt", "d.txt"));
+        assert!(matches("[!a-c].txt", "d.txt"));
+        assert!(!matches("[!a-c].txt", "b.txt"));
+    }
+
+    #[test]
+    fn brace_alternation() {
+        assert!(matches("f.{jpg,png}", "f.jpg"));
+        assert!(matches("f.{jpg,png}", "f.png"));
+        assert!(!matches("f.{jpg,png}", "f.gif"));
+    }
+
+    #[test]
+    fn literal_characters_are_escaped() {
+        assert!(matches("a.b+c", "a.b+c"));
+        assert!(!matches("a.b+c", "aXb+c"));
+    }
+
+    #[test]
+    fn literal_dir_prefix_stops_at_first_wildcard() {
+        assert_eq!(literal_dir_prefix("etc/foo*/bar"), "etc");
+        assert_eq!(literal_dir_prefix("etc/passwd"), "etc");
+        assert_eq!(literal_dir_prefix("*.txt"), "");
+        assert_eq!(literal_dir_prefix("a/b/c"), "a/b");
+    }
+
+    #[test]
+    fn compatible_allows_the_prefix_and_its_parents() {
+        assert!(compatible("", "etc"));
+        assert!(compatible("etc", "etc"));
+        assert!(compatible("e", "etc"));
+        assert!(compatible("etc/foo", "etc"));
+        assert!(!compatible("etc2", "etc"));
+        assert!(!compatible("var", "etc"));
+    }
+}
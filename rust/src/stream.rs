@@ -0,0 +1,178 @@
+/* libguestfs Rust bindings
+ * Copyright (C) 2019 Hiroyuki Katsura <hiroyuki.katsura.0513@gmail.com>
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this library; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA
+ */
+
+use crate::base;
+use crate::error;
+use std::ffi;
+use std::io;
+use std::os::raw::{c_char, c_void};
+use std::slice;
+
+// A few MB per call, well under the libguestfs daemon message size limit.
+const CHUNK_SIZE: usize = 2 * 1024 * 1024;
+
+#[link(name = "guestfs")]
+extern "C" {
+    fn guestfs_pwrite(
+        g: *mut base::guestfs_h,
+        path: *const c_char,
+        content: *const c_char,
+        size: usize,
+        offset: i64,
+    ) -> i32;
+    fn guestfs_pread(
+        g: *mut base::guestfs_h,
+        path: *const c_char,
+        count: usize,
+        offset: i64,
+        size_r: *mut usize,
+    ) -> *mut c_char;
+    fn guestfs_filesize(g: *mut base::guestfs_h, path: *const c_char) -> i64;
+    fn free(buf: *const c_void);
+}
+
+/// Transparent (de)compression to apply while streaming bytes between a
+/// local `Read`/`Write` and a file inside the appliance. The guest-side
+/// bytes are always the plain, uncompressed content; `Compression`
+/// describes the format used on the local side of the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Bzip2,
+    Xz,
+}
+
+impl<'a> base::Handle<'a> {
+    /// Upload `path` inside the appliance from `reader`.
+    ///
+    /// If `compression` is not `Compression::None`, `reader` is assumed to
+    /// already produce data in that compressed format (e.g. a `.tar.gz`
+    /// you have open on disk); it is decompressed on the fly as it is
+    /// pumped into the guest file, so no temporary uncompressed copy is
+    /// ever created.
+    pub fn upload_stream<R: io::Read + 'static>(
+        &self,
+        path: &str,
+        reader: R,
+        compression: Compression,
+    ) -> Result<(), error::Error> {
+        // guestfs_pwrite requires `path` to already exist; create it
+        // (or truncate it to empty, if it already exists) so that
+        // uploading to a fresh path behaves like a real upload rather
+        // than failing with ENOENT.
+        self.write(path, &[])?;
+        let mut reader = decoder(reader, compression);
+        let c_path = ffi::CString::new(path)?;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut offset: i64 = 0;
+        loop {
+            let n = reader.read(&mut buf).map_err(|e| error::Error::UnixError(e, "upload_stream"))?;
+            if n == 0 {
+                break;
+            }
+            // guestfs_pwrite, like pwrite(2), may write fewer than `n`
+            // bytes in one call, so keep retrying the remainder of this
+            // chunk before reading the next one from `reader`.
+            let mut written_in_chunk = 0;
+            while written_in_chunk < n {
+                let written = unsafe {
+                    guestfs_pwrite(
+                        self.g,
+                        c_path.as_ptr(),
+                        buf[written_in_chunk..n].as_ptr() as *const c_char,
+                        n - written_in_chunk,
+                        offset,
+                    )
+                };
+                if written == -1 {
+                    return Err(self.get_error_from_handle("pwrite"));
+                }
+                written_in_chunk += written as usize;
+                offset += written as i64;
+            }
+        }
+        Ok(())
+    }
+
+    /// Download `path` inside the appliance into `writer`.
+    ///
+    /// If `compression` is not `Compression::None`, the plain bytes read
+    /// from the guest are compressed into that format as they are written
+    /// to `writer`, so e.g. a raw disk image can be downloaded directly
+    /// into a `.gz`/`.bz2`/`.xz` file without an intermediate plain copy.
+    pub fn download_stream<W: io::Write + 'static>(
+        &self,
+        path: &str,
+        writer: W,
+        compression: Compression,
+    ) -> Result<(), error::Error> {
+        let mut writer = encoder(writer, compression);
+        let c_path = ffi::CString::new(path)?;
+        let size = unsafe { guestfs_filesize(self.g, c_path.as_ptr()) };
+        if size == -1 {
+            return Err(self.get_error_from_handle("filesize"));
+        }
+        let size = size as u64;
+        let mut offset: u64 = 0;
+        while offset < size {
+            let count = std::cmp::min(CHUNK_SIZE as u64, size - offset) as usize;
+            let mut size_r: usize = 0;
+            let buf = unsafe {
+                guestfs_pread(self.g, c_path.as_ptr(), count, offset as i64, &mut size_r)
+            };
+            if buf.is_null() {
+                return Err(self.get_error_from_handle("pread"));
+            }
+            let slice = unsafe { slice::from_raw_parts(buf as *const u8, size_r) };
+            let result = writer
+                .write_all(slice)
+                .map_err(|e| error::Error::UnixError(e, "download_stream"));
+            unsafe { free(buf as *const c_void) };
+            result?;
+            offset += size_r as u64;
+        }
+        writer
+            .flush()
+            .map_err(|e| error::Error::UnixError(e, "download_stream"))
+    }
+}
+
+fn decoder<R: io::Read + 'static>(reader: R, compression: Compression) -> Box<dyn io::Read> {
+    match compression {
+        Compression::None => Box::new(reader),
+        Compression::Gzip => Box::new(flate2::read::GzDecoder::new(reader)),
+        Compression::Bzip2 => Box::new(bzip2::read::BzDecoder::new(reader)),
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new(reader)),
+    }
+}
+
+fn encoder<W: io::Write + 'static>(writer: W, compression: Compression) -> Box<dyn io::Write> {
+    match compression {
+        Compression::None => Box::new(writer),
+        Compression::Gzip => Box::new(flate2::write::GzEncoder::new(
+            writer,
+            flate2::Compression::default(),
+        )),
+        Compression::Bzip2 => Box::new(bzip2::write::BzEncoder::new(
+            writer,
+            bzip2::Compression::default(),
+        )),
+        Compression::Xz => Box::new(xz2::write::XzEncoder::new(writer, 6)),
+    }
+}
@@ -0,0 +1,125 @@
+/* libguestfs Rust bindings
+ * Copyright (C) 2019 Hiroyuki Katsura <hiroyuki.katsura.0513@gmail.com>
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this library; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA
+ */
+
+use crate::base;
+use crate::error;
+use crate::event;
+use crate::guestfs;
+use std::str;
+
+#[link(name = "guestfs")]
+extern "C" {
+    fn guestfs_delete_event_callback(g: *const base::guestfs_h, eh: i32);
+}
+
+/// A progress update, decoded from the four-element `u64` array that
+/// `Event::Progress` callbacks otherwise receive raw.
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub proc_nr: u64,
+    pub serial: u64,
+    pub position: u64,
+    pub total: u64,
+}
+
+/// A decoded event payload, handed to the closure passed to
+/// `Handle::on_event` instead of the raw `&[u8]`/`&[u64]` buffers.
+pub enum EventPayload<'p> {
+    /// A `Event::Progress` update.
+    Progress(Progress),
+    /// A log/appliance/trace message, decoded as UTF-8.
+    Message(&'p str),
+    /// Anything this layer doesn't know how to decode, passed through.
+    Raw(&'p [u8], &'p [u64]),
+}
+
+fn decode_payload<'p>(event: guestfs::Event, buf: &'p [u8], array: &'p [u64]) -> EventPayload<'p> {
+    if matches!(event, guestfs::Event::Progress) && array.len() == 4 {
+        return EventPayload::Progress(Progress {
+            proc_nr: array[0],
+            serial: array[1],
+            position: array[2],
+            total: array[3],
+        });
+    }
+    if matches!(
+        event,
+        guestfs::Event::Appliance
+            | guestfs::Event::Library
+            | guestfs::Event::Trace
+            | guestfs::Event::Warning
+    ) {
+        if let Ok(s) = str::from_utf8(buf) {
+            return EventPayload::Message(s);
+        }
+    }
+    EventPayload::Raw(buf, array)
+}
+
+/// An RAII guard for a callback registered with `Handle::on_event`: the
+/// callback is unsubscribed automatically when the `Subscription` is
+/// dropped, instead of requiring a matching `delete_event_callback` call.
+///
+/// `Subscription` holds a shared `&'b Handle<'a>`, but `on_event` takes
+/// `&'b mut self`, so the handle stays exclusively borrowed for as long
+/// as the guard is alive: the handle cannot be closed (invalidating the
+/// callback) while the guard exists, and no other method of the handle,
+/// the `&self` action calls such as `copy` or `mkfs` included, can be
+/// called until the `Subscription` is dropped.
+pub struct Subscription<'a, 'b> {
+    handle: &'b base::Handle<'a>,
+    eh: Option<event::EventHandle>,
+}
+
+impl<'a> base::Handle<'a> {
+    /// Subscribe to `events`, decoding each payload before handing it to
+    /// `callback`. This is built on top of `set_event_callback` and keeps
+    /// the same bitmask-filtering semantics; it just saves callers from
+    /// hand-decoding the raw buffers and from having to remember to call
+    /// `delete_event_callback` themselves.
+    pub fn on_event<'b, C>(
+        &'b mut self,
+        events: &[guestfs::Event],
+        callback: C,
+    ) -> Result<Subscription<'a, 'b>, error::Error>
+    where
+        C: Fn(guestfs::Event, EventPayload) + 'a,
+    {
+        let eh = self.set_event_callback(
+            move |event, _eh, buf, array| callback(event, decode_payload(event, buf, array)),
+            events,
+        )?;
+        Ok(Subscription {
+            handle: self,
+            eh: Some(eh),
+        })
+    }
+}
+
+impl<'a, 'b> Drop for Subscription<'a, 'b> {
+    fn drop(&mut self) {
+        if let Some(eh) = self.eh.take() {
+            // Unregisters the callback at the C level directly: going
+            // through the safe `delete_event_callback` would need a
+            // `&mut Handle`, which we deliberately don't hold. The entry
+            // this left behind in the handle's `callbacks` map is
+            // cleaned up when the handle itself is dropped.
+            unsafe { guestfs_delete_event_callback(self.handle.g, eh.raw()) };
+        }
+    }
+}
@@ -0,0 +1,269 @@
+/* libguestfs Rust bindings
+ * Copyright (C) 2019 Hiroyuki Katsura <hiroyuki.katsura.0513@gmail.com>
+ *
+ * This library is free software; you can redistribute it and/or
+ * modify it under the terms of the GNU Lesser General Public
+ * License as published by the Free Software Foundation; either
+ * version 2 of the License, or (at your option) any later version.
+ *
+ * This library is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+ * Lesser General Public License for more details.
+ *
+ * You should have received a copy of the GNU Lesser General Public
+ * License along with this library; if not, write to the Free Software
+ * Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA 02110-1301 USA
+ */
+
+use crate::base;
+use crate::error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Minimal, portable stat information, common to both a guest file and a
+/// host file.
+#[derive(Debug, Clone, Copy)]
+pub struct FileStat {
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    pub size: u64,
+}
+
+/// A backend-agnostic filesystem surface, so provisioning and inspection
+/// code can be written once and run against either a real guest
+/// (`base::Handle`) or the host filesystem (`HostFs`) without caring
+/// which. Kept object-safe (no generic methods) so `&dyn GuestVfs` /
+/// `Box<dyn GuestVfs>` both work, letting libraries accept either backend.
+pub trait GuestVfs {
+    fn read_dir(&self, path: &str) -> Result<Vec<String>, error::Error>;
+    fn stat(&self, path: &str) -> Result<FileStat, error::Error>;
+    fn lstat(&self, path: &str) -> Result<FileStat, error::Error>;
+    fn read(&self, path: &str) -> Result<Vec<u8>, error::Error>;
+    fn write(&self, path: &str, content: &[u8]) -> Result<(), error::Error>;
+    fn mkdir(&self, path: &str) -> Result<(), error::Error>;
+    fn rmdir(&self, path: &str) -> Result<(), error::Error>;
+    fn exists(&self, path: &str) -> Result<bool, error::Error>;
+    fn rename(&self, from: &str, to: &str) -> Result<(), error::Error>;
+    fn symlink(&self, target: &str, linkpath: &str) -> Result<(), error::Error>;
+}
+
+impl<'a> GuestVfs for base::Handle<'a> {
+    fn read_dir(&self, path: &str) -> Result<Vec<String>, error::Error> {
+        let entries = self.readdir(path)?;
+        Ok(entries
+            .into_iter()
+            .map(|e| e.name)
+            .filter(|name| name != "." && name != "..")
+            .collect())
+    }
+
+    fn stat(&self, path: &str) -> Result<FileStat, error::Error> {
+        Ok(FileStat {
+            is_dir: self.is_dir(path)?,
+            is_symlink: self.is_symlink(path)?,
+            size: self.filesize(path)? as u64,
+        })
+    }
+
+    fn lstat(&self, path: &str) -> Result<FileStat, error::Error> {
+        // is_symlink tells us about the link itself, but is_dir/filesize
+        // follow it to the target; for a symlink, lstat must describe the
+        // link, not what it points to, so is_dir is false, and size is
+        // the length of the link's own target string (as a Unix lstat(2)
+        // would report), whenever is_symlink is true.
+        let is_symlink = self.is_symlink(path)?;
+        if is_symlink {
+            Ok(FileStat {
+                is_dir: false,
+                is_symlink: true,
+                size: self.readlink(path)?.len() as u64,
+            })
+        } else {
+            self.stat(path)
+        }
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, error::Error> {
+        self.read_file(path)
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> Result<(), error::Error> {
+        // Resolves to the generated `write` action, not this trait method.
+        self.write(path, content)
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), error::Error> {
+        self.mkdir(path)
+    }
+
+    fn rmdir(&self, path: &str) -> Result<(), error::Error> {
+        self.rmdir(path)
+    }
+
+    fn exists(&self, path: &str) -> Result<bool, error::Error> {
+        self.exists(path)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), error::Error> {
+        self.mv(from, to)
+    }
+
+    fn symlink(&self, target: &str, linkpath: &str) -> Result<(), error::Error> {
+        self.ln_s(target, linkpath)
+    }
+}
+
+/// A `GuestVfs` backed by a directory on the host, so `GuestVfs`-generic
+/// code can be exercised against a plain directory in unit tests before
+/// being pointed at a real disk image.
+pub struct HostFs {
+    root: PathBuf,
+}
+
+impl HostFs {
+    pub fn new<P: Into<PathBuf>>(root: P) -> HostFs {
+        HostFs { root: root.into() }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+}
+
+impl GuestVfs for HostFs {
+    fn read_dir(&self, path: &str) -> Result<Vec<String>, error::Error> {
+        let mut names = Vec::new();
+        for entry in
+            fs::read_dir(self.resolve(path)).map_err(|e| error::Error::UnixError(e, "read_dir"))?
+        {
+            let entry = entry.map_err(|e| error::Error::UnixError(e, "read_dir"))?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileStat, error::Error> {
+        let meta =
+            fs::metadata(self.resolve(path)).map_err(|e| error::Error::UnixError(e, "stat"))?;
+        Ok(FileStat {
+            is_dir: meta.is_dir(),
+            is_symlink: false,
+            size: meta.len(),
+        })
+    }
+
+    fn lstat(&self, path: &str) -> Result<FileStat, error::Error> {
+        let meta = fs::symlink_metadata(self.resolve(path))
+            .map_err(|e| error::Error::UnixError(e, "lstat"))?;
+        Ok(FileStat {
+            is_dir: meta.is_dir(),
+            is_symlink: meta.file_type().is_symlink(),
+            size: meta.len(),
+        })
+    }
+
+    fn read(&self, path: &str) -> Result<Vec<u8>, error::Error> {
+        fs::read(self.resolve(path)).map_err(|e| error::Error::UnixError(e, "read"))
+    }
+
+    fn write(&self, path: &str, content: &[u8]) -> Result<(), error::Error> {
+        fs::write(self.resolve(path), content).map_err(|e| error::Error::UnixError(e, "write"))
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), error::Error> {
+        fs::create_dir(self.resolve(path)).map_err(|e| error::Error::UnixError(e, "mkdir"))
+    }
+
+    fn rmdir(&self, path: &str) -> Result<(), error::Error> {
+        fs::remove_dir(self.resolve(path)).map_err(|e| error::Error::UnixError(e, "rmdir"))
+    }
+
+    fn exists(&self, path: &str) -> Result<bool, error::Error> {
+        Ok(self.resolve(path).exists())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), error::Error> {
+        fs::rename(self.resolve(from), self.resolve(to))
+            .map_err(|e| error::Error::UnixError(e, "rename"))
+    }
+
+    #[cfg(unix)]
+    fn symlink(&self, target: &str, linkpath: &str) -> Result<(), error::Error> {
+        std::os::unix::fs::symlink(target, self.resolve(linkpath))
+            .map_err(|e| error::Error::UnixError(e, "symlink"))
+    }
+
+    #[cfg(not(unix))]
+    fn symlink(&self, _target: &str, _linkpath: &str) -> Result<(), error::Error> {
+        Err(error::unix_error("symlink"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn tmp_dir() -> PathBuf {
+        static COUNTER: AtomicU32 = AtomicU32::new(0);
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = std::env::temp_dir().join(format!(
+            "guestfs-vfs-test-{}-{}",
+            std::process::id(),
+            n
+        ));
+        fs::create_dir_all(&dir).expect("create temp dir");
+        dir
+    }
+
+    #[test]
+    fn mkdir_read_dir_and_exists() {
+        let root = tmp_dir();
+        let vfs = HostFs::new(root.clone());
+        assert!(!vfs.exists("/sub").unwrap());
+        vfs.mkdir("/sub").unwrap();
+        assert!(vfs.exists("/sub").unwrap());
+        assert_eq!(vfs.read_dir("/").unwrap(), vec!["sub".to_string()]);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn write_read_and_stat_a_file() {
+        let root = tmp_dir();
+        let vfs = HostFs::new(root.clone());
+        vfs.write("/hello.txt", b"hello").unwrap();
+        assert_eq!(vfs.read("/hello.txt").unwrap(), b"hello");
+        let stat = vfs.stat("/hello.txt").unwrap();
+        assert!(!stat.is_dir);
+        assert_eq!(stat.size, 5);
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[test]
+    fn rename_moves_a_file() {
+        let root = tmp_dir();
+        let vfs = HostFs::new(root.clone());
+        vfs.write("/a.txt", b"content").unwrap();
+        vfs.rename("/a.txt", "/b.txt").unwrap();
+        assert!(!vfs.exists("/a.txt").unwrap());
+        assert_eq!(vfs.read("/b.txt").unwrap(), b"content");
+        fs::remove_dir_all(&root).ok();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn lstat_reports_the_link_not_its_target() {
+        let root = tmp_dir();
+        let vfs = HostFs::new(root.clone());
+        vfs.mkdir("/target_dir").unwrap();
+        vfs.symlink("target_dir", "/link").unwrap();
+        let lstat = vfs.lstat("/link").unwrap();
+        assert!(lstat.is_symlink);
+        assert!(!lstat.is_dir);
+        let stat = vfs.stat("/link").unwrap();
+        assert!(stat.is_dir);
+        fs::remove_dir_all(&root).ok();
+    }
+}